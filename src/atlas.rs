@@ -0,0 +1,319 @@
+//! Shared dynamic texture atlas for many small user images (icons, glyph sprites, ...).
+//!
+//! Registering each small image as its own [sdl3::render::Texture] gives every one of them a
+//! distinct [imgui::TextureId] and thus its own draw call. [Atlas] instead packs them into one
+//! backing texture via shelf packing, so every sprite it holds shares a single `TextureId` and
+//! can be batched together by [Renderer::render].
+
+use crate::{RenderError, RenderResult, Renderer};
+
+/// A horizontal strip of the atlas at a fixed `y`/`height`, filled left to right.
+struct Shelf {
+    y: u32,
+    height: u32,
+    x_cursor: u32,
+}
+
+/// A handle to a sprite packed into an [Atlas] by [Atlas::insert].
+///
+/// This only records the sprite's pixel-space rectangle, not normalized uv coordinates, so it
+/// never goes stale: the atlas may grow (and thus change what `width`/`height` normalize
+/// against) after the sprite was inserted, so always call [Atlas::uv] to get current `uv0`/`uv1`
+/// rather than caching uvs computed at insert time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sprite {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// A shelf-packed atlas of small RGBA32 images, backed by a single [sdl3::render::Texture]
+/// registered with a [Renderer].
+pub struct Atlas<'a> {
+    texture_id: imgui::TextureId,
+    width: u32,
+    height: u32,
+    shelves: Vec<Shelf>,
+    pixels: Vec<u8>,
+    generation: u64,
+    _marker: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> Atlas<'a> {
+    /// Creates a new `width x height` atlas, initially empty, and registers its backing texture
+    /// with `renderer`.
+    pub fn new(
+        renderer: &mut Renderer<'a>,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, RenderError> {
+        let pixels = vec![0_u8; width as usize * height as usize * 4];
+        let texture_id = renderer.register_rgba(texture_creator, width, height, &pixels)?;
+
+        Ok(Self {
+            texture_id,
+            width,
+            height,
+            shelves: Vec::new(),
+            pixels,
+            generation: 0,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// The [imgui::TextureId] shared by every sprite packed into this atlas.
+    pub fn texture_id(&self) -> imgui::TextureId {
+        self.texture_id
+    }
+
+    /// Bumped every time [Atlas::insert] has to grow the backing texture. Compare against a
+    /// value saved earlier to tell whether any [Sprite] obtained before it might need special
+    /// handling elsewhere (e.g. invalidating a caller-side batch keyed by uv); [Atlas::uv] itself
+    /// always returns correct coordinates regardless of generation.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Packs a `width x height` RGBA32 image into the atlas and uploads it, growing the backing
+    /// texture if it doesn't currently fit.
+    ///
+    /// Returns a [Sprite] handle; pass it to [Atlas::uv] to get the normalized `uv0`/`uv1`
+    /// sub-rectangle to use with `imgui::Image`/`ImageButton`.
+    pub fn insert(
+        &mut self,
+        renderer: &mut Renderer<'a>,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Sprite, RenderError> {
+        let (x, y) = loop {
+            if let Some(pos) = self.place(width, height) {
+                break pos;
+            }
+            self.grow(renderer, texture_creator)?;
+        };
+
+        self.blit(renderer, x, y, width, height, pixels)?;
+        Ok(Sprite {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// Returns the current normalized `uv0`/`uv1` sub-rectangle for `sprite`, relative to this
+    /// atlas's current size. Safe to call even after the atlas has grown since `sprite` was
+    /// obtained from [Atlas::insert].
+    pub fn uv(&self, sprite: Sprite) -> ([f32; 2], [f32; 2]) {
+        (
+            [
+                sprite.x as f32 / self.width as f32,
+                sprite.y as f32 / self.height as f32,
+            ],
+            [
+                (sprite.x + sprite.width) as f32 / self.width as f32,
+                (sprite.y + sprite.height) as f32 / self.height as f32,
+            ],
+        )
+    }
+
+    /// Picks a shelf for a `width x height` rectangle following a shelf-packing heuristic: the
+    /// existing shelf tall enough to hold it with the least wasted vertical space and enough
+    /// remaining width wins; failing that, a new shelf is opened at the current bottom if there
+    /// is still vertical room.
+    fn place(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && self.width - shelf.x_cursor >= width)
+            .min_by_key(|shelf| shelf.height - height);
+
+        if let Some(shelf) = shelf {
+            let x = shelf.x_cursor;
+            shelf.x_cursor += width;
+            return Some((x, shelf.y));
+        }
+
+        let bottom = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if bottom + height > self.height || width > self.width {
+            return None;
+        }
+
+        self.shelves.push(Shelf {
+            y: bottom,
+            height,
+            x_cursor: width,
+        });
+        Some((0, bottom))
+    }
+
+    /// Doubles the atlas's dimensions, re-uploading its existing contents into a new backing
+    /// texture under the same [imgui::TextureId] so already-registered sprites keep drawing
+    /// correctly, and bumps [Self::generation]. Existing [Sprite] handles stay valid since
+    /// [Atlas::uv] always normalizes against the current size.
+    fn grow(
+        &mut self,
+        renderer: &mut Renderer<'a>,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+    ) -> RenderResult {
+        let new_width = self.width * 2;
+        let new_height = self.height * 2;
+        let mut new_pixels = vec![0_u8; new_width as usize * new_height as usize * 4];
+
+        for row in 0..self.height as usize {
+            let src = row * self.width as usize * 4;
+            let dst = row * new_width as usize * 4;
+            new_pixels[dst..dst + self.width as usize * 4]
+                .copy_from_slice(&self.pixels[src..src + self.width as usize * 4]);
+        }
+
+        let new_texture = Renderer::create_static_texture(
+            texture_creator,
+            sdl3_sys::pixels::SDL_PixelFormat::RGBA32,
+            new_width,
+            new_height,
+            &new_pixels,
+        )?;
+        renderer.update_texture(self.texture_id, new_texture);
+
+        self.width = new_width;
+        self.height = new_height;
+        self.pixels = new_pixels;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Writes `pixels` into the CPU-side copy of the atlas and uploads just that sub-rectangle to
+    /// the GPU texture.
+    ///
+    /// Returns [RenderError::PixelDataLength] if `pixels` is shorter than `width * height * 4`
+    /// bytes rather than reading past the end of it.
+    fn blit(
+        &mut self,
+        renderer: &mut Renderer<'a>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> RenderResult {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() < expected {
+            return Err(RenderError::PixelDataLength {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+
+        for row in 0..height as usize {
+            let src = row * width as usize * 4;
+            let dst = ((y as usize + row) * self.width as usize + x as usize) * 4;
+            self.pixels[dst..dst + width as usize * 4]
+                .copy_from_slice(&pixels[src..src + width as usize * 4]);
+        }
+
+        let texture = renderer
+            .texture_map
+            .get_mut(self.texture_id)
+            .expect("atlas texture was removed from the renderer's texture map");
+        let rect = sdl3::rect::Rect::new(x as i32, y as i32, width, height);
+        texture.update(Some(rect), pixels, width as usize * 4)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An atlas with no backing texture, for exercising the pure packing/uv logic without a real
+    /// [Renderer]/[sdl3::render::TextureCreator].
+    fn empty_atlas(width: u32, height: u32) -> Atlas<'static> {
+        Atlas {
+            texture_id: imgui::TextureId::new(0),
+            width,
+            height,
+            shelves: Vec::new(),
+            pixels: vec![0; width as usize * height as usize * 4],
+            generation: 0,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    #[test]
+    fn place_fills_a_shelf_left_to_right() {
+        let mut atlas = empty_atlas(64, 64);
+        assert_eq!(atlas.place(10, 8), Some((0, 0)));
+        assert_eq!(atlas.place(10, 8), Some((10, 0)));
+        assert_eq!(atlas.place(10, 8), Some((20, 0)));
+    }
+
+    #[test]
+    fn place_opens_a_new_shelf_when_the_current_one_is_full() {
+        let mut atlas = empty_atlas(64, 64);
+        assert_eq!(atlas.place(64, 20), Some((0, 0)));
+        assert_eq!(atlas.place(10, 10), Some((0, 20)));
+    }
+
+    #[test]
+    fn place_prefers_the_shelf_with_the_least_wasted_height() {
+        let mut atlas = empty_atlas(100, 200);
+        atlas.place(5, 30).unwrap();
+        atlas.place(5, 80).unwrap();
+        // A 10-tall rect wastes 20px on the height-30 shelf but 70px on the height-80 one.
+        assert_eq!(atlas.place(5, 10), Some((5, 0)));
+    }
+
+    #[test]
+    fn place_returns_none_when_nothing_fits() {
+        let mut atlas = empty_atlas(10, 10);
+        assert_eq!(atlas.place(10, 10), Some((0, 0)));
+        assert_eq!(atlas.place(1, 1), None);
+    }
+
+    #[test]
+    fn place_returns_none_for_a_rect_wider_than_the_atlas() {
+        let mut atlas = empty_atlas(10, 10);
+        assert_eq!(atlas.place(20, 5), None);
+    }
+
+    #[test]
+    fn uv_normalizes_against_the_current_atlas_size() {
+        let atlas = empty_atlas(100, 50);
+        let sprite = Sprite {
+            x: 10,
+            y: 5,
+            width: 20,
+            height: 10,
+        };
+        assert_eq!(atlas.uv(sprite), ([0.1, 0.1], [0.3, 0.3]));
+    }
+
+    #[test]
+    fn uv_tracks_a_size_change_without_needing_a_new_sprite() {
+        let mut atlas = empty_atlas(100, 50);
+        let sprite = Sprite {
+            x: 10,
+            y: 5,
+            width: 20,
+            height: 10,
+        };
+        let before = atlas.uv(sprite);
+        atlas.width *= 2;
+        atlas.height *= 2;
+        assert_eq!(atlas.uv(sprite), ([0.05, 0.05], [0.15, 0.15]));
+        assert_ne!(before, atlas.uv(sprite));
+    }
+
+    #[test]
+    fn generation_starts_at_zero() {
+        assert_eq!(empty_atlas(8, 8).generation(), 0);
+    }
+}
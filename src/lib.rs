@@ -8,7 +8,13 @@ use std::{
     mem::offset_of,
 };
 
-type RenderResult = std::result::Result<(), RenderError>;
+pub(crate) type RenderResult = std::result::Result<(), RenderError>;
+
+mod atlas;
+pub use atlas::Atlas;
+
+mod viewport;
+pub use viewport::ViewportRenderer;
 
 /// A wrapper around various [sdl3] error types
 #[derive(Debug)]
@@ -16,6 +22,14 @@ pub enum RenderError {
     UpdateTexture(sdl3::render::UpdateTextureError),
     TextureValue(sdl3::render::TextureValueError),
     GenericSDL(sdl3::Error),
+    /// `pixels` passed to [Renderer::create_static_texture] (and thus
+    /// [Renderer::register_rgba]/[Renderer::register_bgra]) was shorter than the `width x height`
+    /// it was meant to cover, which would otherwise send [Renderer] past the end of the slice once
+    /// it reaches the FFI boundary in `SDL_UpdateTexture`.
+    PixelDataLength {
+        expected: usize,
+        actual: usize,
+    },
 }
 
 impl From<sdl3::render::UpdateTextureError> for RenderError {
@@ -48,6 +62,12 @@ impl Display for RenderError {
             Self::GenericSDL(e) => {
                 write!(f, "{}", e)
             }
+            Self::PixelDataLength { expected, actual } => {
+                write!(
+                    f,
+                    "pixel data is too short: expected at least {expected} bytes, got {actual}"
+                )
+            }
         }
     }
 }
@@ -58,14 +78,92 @@ impl Error for RenderError {
             Self::UpdateTexture(e) => Some(e),
             Self::TextureValue(e) => Some(e),
             Self::GenericSDL(e) => Some(e),
+            Self::PixelDataLength { .. } => None,
         }
     }
 }
 
+/// A run of consecutive [imgui::DrawCmd::Elements] commands that share a texture, clip rect and
+/// vertex base offset, accumulated so they can be issued as a single `SDL_RenderGeometryRaw` call.
+struct Batch {
+    texture_id: imgui::TextureId,
+    clip_rect: [f32; 4],
+    vtx_offset: usize,
+    idx_start: usize,
+    idx_end: usize,
+}
+
+impl Batch {
+    fn new(params: imgui::DrawCmdParams, count: usize) -> Self {
+        Self {
+            texture_id: params.texture_id,
+            clip_rect: params.clip_rect,
+            vtx_offset: params.vtx_offset,
+            idx_start: params.idx_offset,
+            idx_end: params.idx_offset + count,
+        }
+    }
+
+    /// Whether `params` continues this batch: same texture and clip rect, the same vertex base
+    /// (indices aren't adjusted for a different `vtx_offset`), and a contiguous index range.
+    fn can_extend(&self, params: &imgui::DrawCmdParams) -> bool {
+        self.texture_id == params.texture_id
+            && self.clip_rect == params.clip_rect
+            && self.vtx_offset == params.vtx_offset
+            && self.idx_end == params.idx_offset
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn flush<'a>(
+        self,
+        texture_map: &imgui::Textures<sdl3::render::Texture<'a>>,
+        color_buffer: &[sdl3_sys::pixels::SDL_FColor],
+        canvas: &mut sdl3::render::Canvas<impl sdl3::render::RenderTarget>,
+        vertex_buffer: &[imgui::DrawVert],
+        index_buffer: &[imgui::DrawIdx],
+        pos: &[f32; 2],
+        scale: &[f32; 2],
+        fb_size: (f32, f32),
+    ) -> RenderResult {
+        let clip_min = (
+            (self.clip_rect[0] - pos[0]) * scale[0],
+            (self.clip_rect[1] - pos[1]) * scale[1],
+        );
+        let clip_max = (
+            (self.clip_rect[2] - pos[0]) * scale[0],
+            (self.clip_rect[3] - pos[1]) * scale[1],
+        );
+        if clip_min.0 >= fb_size.0
+            || clip_min.1 >= fb_size.1
+            || clip_max.0 < 0.0
+            || clip_max.1 < 0.0
+        {
+            return Ok(());
+        }
+
+        let rect = sdl3::rect::Rect::new(
+            clip_min.0 as i32,
+            clip_min.1 as i32,
+            (clip_max.0 - clip_min.0) as u32,
+            (clip_max.1 - clip_min.1) as u32,
+        );
+        canvas.set_clip_rect(rect);
+
+        let texture = texture_map.get(self.texture_id);
+        Renderer::render_raw_geometry(
+            canvas,
+            texture,
+            &vertex_buffer[self.vtx_offset..],
+            &color_buffer[self.vtx_offset..],
+            &index_buffer[self.idx_start..self.idx_end],
+        )
+    }
+}
+
 /// Represents the context for the renderer
 pub struct Renderer<'a> {
-    texture_map: imgui::Textures<sdl3::render::Texture<'a>>,
-    color_buffer: Vec<sdl3_sys::pixels::SDL_FColor>,
+    pub(crate) texture_map: imgui::Textures<sdl3::render::Texture<'a>>,
+    pub(crate) color_buffer: Vec<sdl3_sys::pixels::SDL_FColor>,
 }
 
 impl<'a> Renderer<'a> {
@@ -117,6 +215,74 @@ impl<'a> Renderer<'a> {
         })
     }
 
+    /// Recovers from an SDL render-device or render-target reset.
+    ///
+    /// SDL3 invalidates every [sdl3::render::Texture] belonging to a `Canvas` and emits
+    /// `SDL_EVENT_RENDER_DEVICE_RESET` or `SDL_EVENT_RENDER_TARGETS_RESET` when that happens (for
+    /// example on D3D device loss or GL context recreation). Callers should invoke this method as
+    /// soon as they observe either event in their event loop, before the next [Self::render] call.
+    ///
+    /// This rebuilds the font atlas texture from `imgui_context` and puts it back under its
+    /// existing [imgui::TextureId], then calls `reregister_textures` so the caller can re-upload
+    /// their own registered textures (e.g. via [Self::update_texture]) onto the same ids as
+    /// before.
+    ///
+    /// <div class="warning">
+    ///
+    /// Unlike [Self::new], this never replaces the texture map itself, only the textures inside
+    /// it. [imgui::Textures] keeps an internal id counter that [Self::register_texture] and
+    /// [Self::register_rgba] draw new ids from, but that [Self::update_texture] does not advance;
+    /// swapping in a brand new, empty map here (and re-inserting the font atlas into it) would
+    /// desync that counter from the ids `reregister_textures` restores via [Self::update_texture],
+    /// letting a later [Self::register_texture] call silently reassign and clobber one of them.
+    ///
+    /// </div>
+    pub fn handle_device_reset(
+        &mut self,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        imgui_context: &mut imgui::Context,
+        reregister_textures: impl FnOnce(&mut Self) -> RenderResult,
+    ) -> RenderResult {
+        let font_atlas_id = imgui_context.fonts().tex_id;
+        let font_atlas = imgui_context.fonts().build_rgba32_texture();
+        let font_texture = Self::create_static_texture(
+            texture_creator,
+            sdl3_sys::pixels::SDL_PixelFormat::RGBA32,
+            font_atlas.width,
+            font_atlas.height,
+            font_atlas.data,
+        )?;
+        self.texture_map.replace(font_atlas_id, font_texture);
+
+        reregister_textures(self)
+    }
+
+    /// Rebuilds the font atlas texture from the fonts currently configured on `imgui_context`.
+    ///
+    /// [Self::new] only builds the atlas once, so call this after adding or removing fonts at
+    /// runtime to pick up the new font configuration. This removes the old atlas texture from the
+    /// texture map, rebuilds it via `imgui_context.fonts().build_rgba32_texture()`, uploads the
+    /// new texture and updates `imgui_context.fonts().tex_id` to point at it.
+    ///
+    /// <div class="warning">
+    ///
+    /// To keep text crisp after a HiDPI scale change, reconfigure each font's `size_pixels` (or
+    /// `FontConfig::rasterizer_density`, if the fork in use has it) to match the new scale before
+    /// calling this. `imgui::Io::font_global_scale` only scales already-rasterized glyphs at draw
+    /// time and does not affect what `build_rgba32_texture` rasterizes, so changing it and calling
+    /// this method re-rasterizes the exact same bitmap at the exact same resolution.
+    ///
+    /// </div>
+    pub fn rebuild_font_atlas(
+        &mut self,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        imgui_context: &mut imgui::Context,
+    ) -> RenderResult {
+        let old_atlas_id = imgui_context.fonts().tex_id;
+        self.texture_map.remove(old_atlas_id);
+        Self::prepare_font_atlas(texture_creator, imgui_context, &mut self.texture_map)
+    }
+
     /// Renders the `draw_data` to the `canvas`
     ///
     /// <div class="warning">
@@ -172,30 +338,89 @@ impl<'a> Renderer<'a> {
         }
 
         for draw_list in draw_data.draw_lists() {
+            let vertex_buffer = draw_list.vtx_buffer();
+            let index_buffer = draw_list.idx_buffer();
+
+            // Normalize every vertex colour in the draw list once, up front, rather than
+            // redoing the conversion for each individual draw command.
+            self.color_buffer.clear();
+            self.color_buffer.extend(vertex_buffer.iter().map(|vert| {
+                sdl3_sys::pixels::SDL_FColor {
+                    r: vert.col[0] as f32 / 255_f32,
+                    g: vert.col[1] as f32 / 255_f32,
+                    b: vert.col[2] as f32 / 255_f32,
+                    a: vert.col[3] as f32 / 255_f32,
+                }
+            }));
+
+            let mut batch: Option<Batch> = None;
             for command in draw_list.commands() {
                 match command {
-                    imgui::DrawCmd::Elements { count, cmd_params } => {
-                        Self::render_elements(
-                            &self.texture_map,
-                            &mut self.color_buffer,
-                            canvas,
-                            draw_list.vtx_buffer(),
-                            draw_list.idx_buffer(),
-                            count,
-                            &cmd_params,
-                            &draw_data.display_pos,
-                            &draw_data.framebuffer_scale,
-                            (fb_width, fb_height),
-                        )?;
-                    }
+                    imgui::DrawCmd::Elements { count, cmd_params } => match &mut batch {
+                        Some(current) if current.can_extend(&cmd_params) => {
+                            current.idx_end += count;
+                        }
+                        _ => {
+                            if let Some(finished) = batch.replace(Batch::new(cmd_params, count)) {
+                                finished.flush(
+                                    &self.texture_map,
+                                    &self.color_buffer,
+                                    canvas,
+                                    vertex_buffer,
+                                    index_buffer,
+                                    &draw_data.display_pos,
+                                    &draw_data.framebuffer_scale,
+                                    (fb_width, fb_height),
+                                )?;
+                            }
+                        }
+                    },
                     imgui::DrawCmd::ResetRenderState => {
+                        if let Some(finished) = batch.take() {
+                            finished.flush(
+                                &self.texture_map,
+                                &self.color_buffer,
+                                canvas,
+                                vertex_buffer,
+                                index_buffer,
+                                &draw_data.display_pos,
+                                &draw_data.framebuffer_scale,
+                                (fb_width, fb_height),
+                            )?;
+                        }
                         Self::set_up_render_state(canvas);
                     }
-                    imgui::DrawCmd::RawCallback { callback, raw_cmd } => unsafe {
-                        callback(draw_list.raw(), raw_cmd);
-                    },
+                    imgui::DrawCmd::RawCallback { callback, raw_cmd } => {
+                        if let Some(finished) = batch.take() {
+                            finished.flush(
+                                &self.texture_map,
+                                &self.color_buffer,
+                                canvas,
+                                vertex_buffer,
+                                index_buffer,
+                                &draw_data.display_pos,
+                                &draw_data.framebuffer_scale,
+                                (fb_width, fb_height),
+                            )?;
+                        }
+                        unsafe {
+                            callback(draw_list.raw(), raw_cmd);
+                        }
+                    }
                 }
             }
+            if let Some(finished) = batch.take() {
+                finished.flush(
+                    &self.texture_map,
+                    &self.color_buffer,
+                    canvas,
+                    vertex_buffer,
+                    index_buffer,
+                    &draw_data.display_pos,
+                    &draw_data.framebuffer_scale,
+                    (fb_width, fb_height),
+                )?;
+            }
         }
 
         canvas.set_viewport(backup.viewport);
@@ -203,76 +428,14 @@ impl<'a> Renderer<'a> {
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn render_elements(
-        texture_map: &imgui::Textures<sdl3::render::Texture<'a>>,
-        color_buffer: &mut Vec<sdl3_sys::pixels::SDL_FColor>,
-        canvas: &mut sdl3::render::Canvas<impl sdl3::render::RenderTarget>,
-        vertex_buffer: &[imgui::DrawVert],
-        index_buffer: &[imgui::DrawIdx],
-        elem_count: usize,
-        elem_params: &imgui::DrawCmdParams,
-        pos: &[f32; 2],
-        scale: &[f32; 2],
-        fb_size: (f32, f32),
-    ) -> RenderResult {
-        let imgui::DrawCmdParams {
-            clip_rect,
-            texture_id,
-            vtx_offset,
-            idx_offset,
-        } = elem_params;
-
-        let clip_min = (
-            (clip_rect[0] - pos[0]) * scale[0],
-            (clip_rect[1] - pos[1]) * scale[1],
-        );
-        let clip_max = (
-            (clip_rect[2] - pos[0]) * scale[0],
-            (clip_rect[3] - pos[1]) * scale[1],
-        );
-        if clip_min.0 >= fb_size.0
-            || clip_min.1 >= fb_size.1
-            || clip_max.0 < 0.0
-            || clip_max.1 < 0.0
-        {
-            return Ok(());
-        }
-
-        let rect = sdl3::rect::Rect::new(
-            clip_min.0 as i32,
-            clip_min.1 as i32,
-            (clip_max.0 - clip_min.0) as u32,
-            (clip_max.1 - clip_min.1) as u32,
-        );
-        canvas.set_clip_rect(rect);
-
-        let texture = texture_map.get(*texture_id);
-        Self::render_raw_geometry(
-            canvas,
-            color_buffer,
-            texture,
-            &vertex_buffer[*vtx_offset..],
-            &index_buffer[*idx_offset..idx_offset + elem_count],
-        )
-    }
-
     fn render_raw_geometry(
         canvas: &mut sdl3::render::Canvas<impl sdl3::render::RenderTarget>,
-        color_buffer: &mut Vec<sdl3_sys::pixels::SDL_FColor>,
         texture: Option<&sdl3::render::Texture>,
         vertices: &[imgui::DrawVert],
+        colors: &[sdl3_sys::pixels::SDL_FColor],
         indices: &[imgui::DrawIdx],
     ) -> RenderResult {
         let vert_stride = size_of::<imgui::DrawVert>() as c_int;
-        color_buffer.clear();
-        // Normalize colours to SDL_Fcolor format 
-        color_buffer.extend(vertices.iter().map(|vert| sdl3_sys::pixels::SDL_FColor {
-            r: vert.col[0] as f32 / 255_f32,
-            g: vert.col[1] as f32 / 255_f32,
-            b: vert.col[2] as f32 / 255_f32,
-            a: vert.col[3] as f32 / 255_f32,
-        }));
 
         let renderer = canvas.raw();
         let texture = texture.map_or(std::ptr::null_mut(), |texture| texture.raw());
@@ -284,7 +447,7 @@ impl<'a> Renderer<'a> {
             vertices.as_ptr().byte_add(offset_of!(imgui::DrawVert, uv)) as *const c_float
         };
         let idx = indices.as_ptr() as *const c_void;
-        let colors = color_buffer.as_ptr();
+        let colors = colors.as_ptr();
 
         unsafe {
             sdl3_sys::render::SDL_RenderGeometryRaw(
@@ -317,23 +480,112 @@ impl<'a> Renderer<'a> {
         texture_map: &mut imgui::Textures<sdl3::render::Texture<'a>>,
     ) -> RenderResult {
         let font_atlas = imgui_context.fonts().build_rgba32_texture();
-        let rgba32_format: sdl3::pixels::PixelFormat =
-            sdl3_sys::pixels::SDL_PixelFormat::RGBA32.try_into()?;
-        let mut font_texture =
-            creator.create_texture_static(rgba32_format, font_atlas.width, font_atlas.height)?;
-
-        font_texture.update(
-            None,
+        let font_texture = Self::create_static_texture(
+            creator,
+            sdl3_sys::pixels::SDL_PixelFormat::RGBA32,
+            font_atlas.width,
+            font_atlas.height,
             font_atlas.data,
-            rgba32_format.byte_size_of_pixels(font_atlas.width as usize),
         )?;
 
-        font_texture.set_blend_mode(sdl3::render::BlendMode::Blend);
-        font_texture.set_scale_mode(sdl3::render::ScaleMode::Linear);
-
         let id = texture_map.insert(font_texture);
         imgui_context.fonts().tex_id = id;
         Ok(())
     }
-}
 
+    /// Creates a static texture with the given pixel `format`, uploads `pixels` into it and sets
+    /// up the blend/scale modes shared by the font atlas and user-registered textures.
+    ///
+    /// Returns [RenderError::PixelDataLength] if `pixels` is shorter than `width * height` worth
+    /// of pixels in `format`, rather than handing a too-short slice to `SDL_UpdateTexture`.
+    pub(crate) fn create_static_texture(
+        creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        format: sdl3_sys::pixels::SDL_PixelFormat,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<sdl3::render::Texture<'a>, RenderError> {
+        let format: sdl3::pixels::PixelFormat = format.try_into()?;
+        let pitch = format.byte_size_of_pixels(width as usize);
+        let expected = pitch * height as usize;
+        if pixels.len() < expected {
+            return Err(RenderError::PixelDataLength {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+
+        let mut texture = creator.create_texture_static(format, width, height)?;
+
+        texture.update(None, pixels, pitch)?;
+
+        texture.set_blend_mode(sdl3::render::BlendMode::Blend);
+        texture.set_scale_mode(sdl3::render::ScaleMode::Linear);
+
+        Ok(texture)
+    }
+
+    /// Registers `texture` with the renderer, returning the [imgui::TextureId] to pass to
+    /// `imgui::Image`/`ImageButton` so it draws the texture.
+    pub fn register_texture(&mut self, texture: sdl3::render::Texture<'a>) -> imgui::TextureId {
+        self.texture_map.insert(texture)
+    }
+
+    /// Replaces the texture previously registered as `id`, returning the old texture if one was
+    /// present. Use this to refresh the contents of a texture that ImGui already references.
+    pub fn update_texture(
+        &mut self,
+        id: imgui::TextureId,
+        texture: sdl3::render::Texture<'a>,
+    ) -> Option<sdl3::render::Texture<'a>> {
+        self.texture_map.replace(id, texture)
+    }
+
+    /// Removes and returns the texture registered as `id`, if any.
+    pub fn remove_texture(&mut self, id: imgui::TextureId) -> Option<sdl3::render::Texture<'a>> {
+        self.texture_map.remove(id)
+    }
+
+    /// Creates a static RGBA32 texture from already-decoded, tightly-packed `pixels`
+    /// (`width * height * 4` bytes, red first) and registers it, returning the [imgui::TextureId]
+    /// to use with `imgui::Image`/`ImageButton`.
+    pub fn register_rgba(
+        &mut self,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<imgui::TextureId, RenderError> {
+        let texture = Self::create_static_texture(
+            texture_creator,
+            sdl3_sys::pixels::SDL_PixelFormat::RGBA32,
+            width,
+            height,
+            pixels,
+        )?;
+        Ok(self.register_texture(texture))
+    }
+
+    /// Creates a static BGRA32 texture from already-decoded, tightly-packed `pixels`
+    /// (`width * height * 4` bytes, blue first) and registers it, returning the [imgui::TextureId]
+    /// to use with `imgui::Image`/`ImageButton`.
+    ///
+    /// Prefer this over [Self::register_rgba] when feeding decoded image data (e.g. from a PNG or
+    /// JPEG decoder) whose channel order is already BGRA, so no channel swizzle is needed.
+    pub fn register_bgra(
+        &mut self,
+        texture_creator: &'a sdl3::render::TextureCreator<impl std::any::Any>,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<imgui::TextureId, RenderError> {
+        let texture = Self::create_static_texture(
+            texture_creator,
+            sdl3_sys::pixels::SDL_PixelFormat::BGRA32,
+            width,
+            height,
+            pixels,
+        )?;
+        Ok(self.register_texture(texture))
+    }
+}
@@ -0,0 +1,245 @@
+//! Multi-viewport rendering support for ImGui docking/platform windows.
+//!
+//! When the application enables [imgui::ConfigFlags::VIEWPORTS_ENABLE], ImGui creates extra
+//! [imgui::Viewport]s (for panels the user drags outside the main window) that each need to be
+//! drawn into their own OS window. [ViewportRenderer] registers renderer callbacks on ImGui's
+//! `PlatformIO` that create, resize, render and destroy one SDL window + [sdl3::render::Canvas]
+//! per secondary viewport, the same way the rest of this crate renders the main one.
+//!
+//! <div class="warning">
+//!
+//! This only supplies the `Renderer_*` callbacks that ImGui's own platform-window update calls
+//! into; it doesn't drive them itself. Call `imgui_context.update_platform_windows()` and ImGui's
+//! `igRenderPlatformWindowsDefault` once per frame, after presenting the main viewport, to
+//! actually create/resize/render/present secondary viewports.
+//!
+//! </div>
+
+use std::{collections::HashMap, ffi::c_void};
+
+use crate::{RenderError, RenderResult, Renderer};
+
+/// The resources kept for a single secondary ImGui viewport.
+struct ViewportWindow<'a> {
+    canvas: sdl3::render::Canvas<sdl3::video::Window>,
+    texture_map: imgui::Textures<sdl3::render::Texture<'a>>,
+    // Boxed so its heap address stays stable even if `self` moves; `texture_map`'s textures
+    // borrow from it via `Self::creator`, with the borrow's lifetime extended through that
+    // method. Declared after `texture_map` so Rust drops the borrowing textures before the
+    // creator they borrow from (struct fields are dropped in declaration order).
+    texture_creator: Box<sdl3::render::TextureCreator<sdl3::video::WindowContext>>,
+}
+
+impl<'a> ViewportWindow<'a> {
+    fn new(
+        video: &sdl3::VideoSubsystem,
+        viewport: &imgui::sys::ImGuiViewport,
+    ) -> Result<Self, RenderError> {
+        let mut builder = video.window(
+            "imgui-rs-sdl3-renderer viewport",
+            viewport.Size[0].max(1.0) as u32,
+            viewport.Size[1].max(1.0) as u32,
+        );
+        builder
+            .position(viewport.Pos[0] as i32, viewport.Pos[1] as i32)
+            .borderless()
+            .hidden()
+            .resizable();
+
+        let mut window = builder
+            .build()
+            .map_err(|e| RenderError::GenericSDL(e.into()))?;
+        // The window is built hidden so it isn't shown mid-position/size setup; show it now that
+        // it's ready, otherwise a dragged-out viewport would never become visible.
+        window.show();
+
+        let canvas = window.into_canvas();
+        let texture_creator = Box::new(canvas.texture_creator());
+
+        let mut this = Self {
+            canvas,
+            texture_map: imgui::Textures::new(),
+            texture_creator,
+        };
+        this.sync_font_atlas()?;
+        Ok(this)
+    }
+
+    /// Borrows `self.texture_creator` with its lifetime extended to `'a`.
+    ///
+    /// This is sound only because `texture_creator` is boxed, so its address is stable across any
+    /// move of `self`, is never replaced or dropped before `self` itself is, and is declared after
+    /// `texture_map` so its borrowed textures are always dropped first.
+    fn creator(&self) -> &'a sdl3::render::TextureCreator<sdl3::video::WindowContext> {
+        unsafe { &*(self.texture_creator.as_ref() as *const _) }
+    }
+
+    /// Re-uploads the current font atlas into this viewport's own texture map, under the same
+    /// [imgui::TextureId] the main [Renderer]'s atlas uses, so draw commands that reference it
+    /// (i.e. all text) resolve to a real texture instead of missing the map entirely.
+    ///
+    /// ImGui shares one `ImFontAtlas` across every viewport, so the pixel data is read directly
+    /// off it via the raw ImGui API rather than needing a reference back to the main `Renderer`.
+    fn sync_font_atlas(&mut self) -> RenderResult {
+        unsafe {
+            let io = imgui::sys::igGetIO();
+            let fonts = (*io).Fonts;
+            let (width, height) = ((*fonts).TexWidth, (*fonts).TexHeight);
+            if width <= 0 || height <= 0 || (*fonts).TexPixelsRGBA32.is_null() {
+                return Ok(());
+            }
+            let pixels = std::slice::from_raw_parts(
+                (*fonts).TexPixelsRGBA32 as *const u8,
+                width as usize * height as usize * 4,
+            );
+            let texture_id = imgui::TextureId::new((*fonts).TexID as usize);
+
+            let texture = Renderer::create_static_texture(
+                self.creator(),
+                sdl3_sys::pixels::SDL_PixelFormat::RGBA32,
+                width as u32,
+                height as u32,
+                pixels,
+            )?;
+            self.texture_map.replace(texture_id, texture);
+        }
+        Ok(())
+    }
+}
+
+/// Owns the per-viewport SDL windows, canvases and texture maps created for ImGui's secondary
+/// platform viewports, and registers the callbacks ImGui needs to drive them.
+///
+/// Construct this once after the main [Renderer] via [ViewportRenderer::install], and keep it
+/// alive for as long as `imgui_context` has `VIEWPORTS_ENABLE` set.
+///
+/// <div class="warning">
+///
+/// Call [ViewportRenderer::uninstall] before dropping this (and before `imgui_context` itself is
+/// dropped). `install` hands ImGui's `PlatformIO` a raw pointer to this struct; `uninstall` is the
+/// only thing that clears it. A `ViewportRenderer` dropped without calling `uninstall` first
+/// leaves that pointer dangling, and ImGui will dereference it on the next viewport update.
+///
+/// </div>
+pub struct ViewportRenderer<'a> {
+    video: sdl3::VideoSubsystem,
+    windows: HashMap<u32, ViewportWindow<'a>>,
+}
+
+impl<'a> ViewportRenderer<'a> {
+    /// Registers the `Renderer_*` callbacks on `imgui_context`'s `PlatformIO` so ImGui can drive
+    /// window creation, resizing, rendering and presentation for secondary viewports.
+    ///
+    /// `video` is used to create the native SDL window backing each secondary viewport.
+    pub fn install(imgui_context: &mut imgui::Context, video: sdl3::VideoSubsystem) -> Box<Self> {
+        let mut this = Box::new(Self {
+            video,
+            windows: HashMap::new(),
+        });
+
+        let platform_io = imgui_context.platform_io_mut();
+        platform_io.renderer_user_data = this.as_mut() as *mut Self as *mut c_void;
+        platform_io.renderer_create_window = Some(Self::create_window);
+        platform_io.renderer_destroy_window = Some(Self::destroy_window);
+        platform_io.renderer_set_window_size = Some(Self::set_window_size);
+        platform_io.renderer_render_window = Some(Self::render_window);
+        platform_io.renderer_swap_buffers = Some(Self::swap_buffers);
+
+        this
+    }
+
+    /// Clears the callbacks this installed on `imgui_context`'s `PlatformIO` and destroys any
+    /// windows that are still open. Call this before dropping the `ViewportRenderer`.
+    pub fn uninstall(mut self: Box<Self>, imgui_context: &mut imgui::Context) {
+        let platform_io = imgui_context.platform_io_mut();
+        platform_io.renderer_user_data = std::ptr::null_mut();
+        platform_io.renderer_create_window = None;
+        platform_io.renderer_destroy_window = None;
+        platform_io.renderer_set_window_size = None;
+        platform_io.renderer_render_window = None;
+        platform_io.renderer_swap_buffers = None;
+        self.windows.clear();
+    }
+
+    /// Recovers the `&mut Self` [Self::install] stashed in `PlatformIO::renderer_user_data`.
+    ///
+    /// The returned reference is bound to the caller's own lifetime, not `'static`: nothing about
+    /// the callback's raw pointer actually outlives the `ViewportRenderer` it points at, and
+    /// returning `'static` here would wrongly force `ViewportRenderer<'a>` itself to be
+    /// `'a: 'static`.
+    unsafe fn this_from_io<'b>(io: *mut imgui::sys::ImGuiPlatformIO) -> &'b mut Self {
+        unsafe { &mut *((*io).RendererUserData as *mut Self) }
+    }
+
+    unsafe extern "C" fn create_window(viewport: *mut imgui::sys::ImGuiViewport) {
+        unsafe {
+            let io = imgui::sys::igGetPlatformIO();
+            let this = Self::this_from_io(io);
+            if let Ok(window) = ViewportWindow::new(&this.video, &*viewport) {
+                this.windows.insert((*viewport).ID, window);
+            }
+        }
+    }
+
+    unsafe extern "C" fn destroy_window(viewport: *mut imgui::sys::ImGuiViewport) {
+        unsafe {
+            let io = imgui::sys::igGetPlatformIO();
+            let this = Self::this_from_io(io);
+            this.windows.remove(&(*viewport).ID);
+        }
+    }
+
+    unsafe extern "C" fn set_window_size(
+        viewport: *mut imgui::sys::ImGuiViewport,
+        size: imgui::sys::ImVec2,
+    ) {
+        unsafe {
+            let io = imgui::sys::igGetPlatformIO();
+            let this = Self::this_from_io(io);
+            if let Some(window) = this.windows.get_mut(&(*viewport).ID) {
+                let _ = window
+                    .canvas
+                    .window_mut()
+                    .set_size(size.x.max(1.0) as u32, size.y.max(1.0) as u32);
+            }
+        }
+    }
+
+    unsafe extern "C" fn render_window(
+        viewport: *mut imgui::sys::ImGuiViewport,
+        _render_arg: *mut c_void,
+    ) {
+        unsafe {
+            let io = imgui::sys::igGetPlatformIO();
+            let this = Self::this_from_io(io);
+            let Some(window) = this.windows.get_mut(&(*viewport).ID) else {
+                return;
+            };
+            let draw_data = &*((*viewport).DrawData as *const imgui::DrawData);
+            window.canvas.clear();
+            let mut renderer = Renderer {
+                texture_map: std::mem::take(&mut window.texture_map),
+                color_buffer: Vec::new(),
+            };
+            let _ = renderer.render(draw_data, &mut window.canvas);
+            window.texture_map = renderer.texture_map;
+        }
+    }
+
+    /// ImGui calls `Renderer_RenderWindow` and then `Renderer_SwapBuffers` separately for each
+    /// platform window, the same split the main viewport does with [Renderer::render] followed by
+    /// `canvas.present()`. Without registering this, secondary viewports draw but never actually
+    /// show anything on screen.
+    unsafe extern "C" fn swap_buffers(
+        viewport: *mut imgui::sys::ImGuiViewport,
+        _render_arg: *mut c_void,
+    ) {
+        unsafe {
+            let io = imgui::sys::igGetPlatformIO();
+            let this = Self::this_from_io(io);
+            if let Some(window) = this.windows.get_mut(&(*viewport).ID) {
+                window.canvas.present();
+            }
+        }
+    }
+}